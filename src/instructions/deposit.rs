@@ -1,15 +1,83 @@
 use core::mem::size_of;
 
 use pinocchio::{
-    ProgramResult, account_info::AccountInfo, program_error::ProgramError,
-    pubkey::find_program_address,
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{Seed, Signer},
+    program_error::ProgramError,
+    pubkey::{Pubkey, find_program_address},
+    sysvars::{Sysvar, rent::Rent},
 };
-use pinocchio_secp256r1_instruction::{SECP256R1_COMPRESSED_PUBKEY_LENGTH, Secp256r1Pubkey};
-use pinocchio_system::instructions::Transfer;
+use pinocchio_secp256r1_instruction::SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+use pinocchio_system::instructions::{Allocate, Assign, Transfer};
+
+use crate::state::{MAX_SIGNERS, MAX_TARGETS, VaultConfig, VaultRecord, VaultVesting, VaultWhitelist, hashv};
+
+/// Brings a vault's state PDA to a program-owned, rent-exempt, `space`-byte
+/// account, returning whether it was already initialized by a prior call.
+///
+/// Gating initialization on `lamports() == 0` lets anyone grief a deposit
+/// by pre-funding a state PDA with a stray lamport before the depositor's
+/// transaction lands: every PDA address is deterministic from the public
+/// member-set commitment, so the `CreateAccount` (and the `VaultConfig`
+/// / `VaultVesting` / `VaultWhitelist` write that used to ride along with
+/// it) would silently be skipped, while the vault itself still got
+/// funded — leaving a permanently unwithdrawable vault with no error
+/// surfaced to the depositor. Instead, trust only actual ownership: an
+/// account already owned by this program is assumed initialized; a
+/// system-owned account with no data is topped up to rent-exemption and
+/// allocated/assigned regardless of its current lamport balance, and any
+/// other account is rejected. This tolerates a
+/// pre-funded account the same way `CreateAccountWithSeed` would.
+fn init_state_account(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    space: usize,
+    signers: &[Signer],
+) -> Result<bool, ProgramError> {
+    if account.is_owned_by(&crate::ID) {
+        if account.data_len() != space {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        return Ok(true);
+    }
+
+    if !account.is_owned_by(&pinocchio_system::ID) || account.data_len() != 0 {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(account.lamports());
+    if shortfall > 0 {
+        Transfer {
+            from: payer,
+            to: account,
+            lamports: shortfall,
+        }
+        .invoke()?;
+    }
+
+    Allocate {
+        account,
+        space: space as u64,
+    }
+    .invoke_signed(signers)?;
+
+    Assign {
+        account,
+        owner: &crate::ID,
+    }
+    .invoke_signed(signers)?;
+
+    Ok(false)
+}
 
 pub struct DepositAccounts<'a> {
     pub payer: &'a AccountInfo,
     pub vault: &'a AccountInfo,
+    pub record: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub vesting: &'a AccountInfo,
+    pub whitelist: &'a AccountInfo,
     pub system_program: &'a AccountInfo,
 }
 
@@ -17,7 +85,7 @@ impl<'a> TryFrom<&'a [AccountInfo]> for DepositAccounts<'a> {
     type Error = ProgramError;
 
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-        let [payer, vault, system_program] = accounts else {
+        let [payer, vault, record, config, vesting, whitelist, system_program] = accounts else {
             return Err(ProgramError::NotEnoughAccountKeys);
         };
 
@@ -38,45 +106,104 @@ impl<'a> TryFrom<&'a [AccountInfo]> for DepositAccounts<'a> {
         Ok(Self {
             payer,
             vault,
+            record,
+            config,
+            vesting,
+            whitelist,
             system_program,
         })
     }
 }
 
-pub struct DepositInstructionData {
-    pub pubkey: Secp256r1Pubkey,
+pub struct DepositInstructionData<'a> {
     pub amount: u64,
+    pub threshold: u8,
+    pub start_ts: i64,
+    pub cliff_ts: i64,
+    pub end_ts: i64,
+    /// Raw, strictly-ascending-sorted concatenation of compressed
+    /// secp256r1 member pubkeys (`num_members * 33` bytes).
+    pub members: &'a [u8],
+    /// Raw concatenation of program ids allowed to receive relayed
+    /// withdrawals (`num_targets * 32` bytes).
+    pub targets: &'a [u8],
 }
 
-impl<'a> TryFrom<&'a [u8]> for DepositInstructionData {
+impl<'a> TryFrom<&'a [u8]> for DepositInstructionData<'a> {
     type Error = ProgramError;
 
     fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
-        if data.len() != size_of::<Secp256r1Pubkey>() + size_of::<u64>() {
+        // amount(8) || threshold(1) || num_members(1) || start_ts(8)
+        // || cliff_ts(8) || end_ts(8) || members(num_members * 33)
+        // || num_targets(1) || targets(num_targets * 32)
+        if data.len() < size_of::<u64>() + 2 + size_of::<i64>() * 3 {
             return Err(ProgramError::InvalidInstructionData);
         }
 
-        let pubkey =
-            Secp256r1Pubkey::try_from(&data[0..SECP256R1_COMPRESSED_PUBKEY_LENGTH]).unwrap();
+        let amount = u64::from_le_bytes(data[0..8].try_into().unwrap());
+        if amount == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
-        let amount = u64::from_le_bytes(
-            data[SECP256R1_COMPRESSED_PUBKEY_LENGTH
-                ..SECP256R1_COMPRESSED_PUBKEY_LENGTH + size_of::<u64>()]
-                .try_into()
-                .unwrap(),
-        );
+        let threshold = data[8];
+        let num_members = data[9] as usize;
+        if num_members == 0 || num_members > MAX_SIGNERS || threshold == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        if threshold as usize > num_members {
+            return Err(ProgramError::InvalidInstructionData);
+        }
 
-        if amount == 0 {
+        let start_ts = i64::from_le_bytes(data[10..18].try_into().unwrap());
+        let cliff_ts = i64::from_le_bytes(data[18..26].try_into().unwrap());
+        let end_ts = i64::from_le_bytes(data[26..34].try_into().unwrap());
+        if cliff_ts < start_ts || end_ts <= cliff_ts {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let members_end = 34 + num_members * SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+        if data.len() < members_end + 1 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let members = &data[34..members_end];
+
+        // Members must be supplied in canonical, strictly ascending order
+        // so the commitment derived from them is deterministic
+        let mut prev: Option<&[u8]> = None;
+        for member in members.chunks(SECP256R1_COMPRESSED_PUBKEY_LENGTH) {
+            if let Some(prev) = prev {
+                if prev >= member {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+            }
+            prev = Some(member);
+        }
+
+        let num_targets = data[members_end] as usize;
+        if num_targets == 0 || num_targets > MAX_TARGETS {
             return Err(ProgramError::InvalidInstructionData);
         }
 
-        Ok(Self { pubkey, amount })
+        let targets = &data[members_end + 1..];
+        if targets.len() != num_targets * size_of::<Pubkey>() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            amount,
+            threshold,
+            start_ts,
+            cliff_ts,
+            end_ts,
+            members,
+            targets,
+        })
     }
 }
 
 pub struct Deposit<'a> {
     pub accounts: DepositAccounts<'a>,
-    pub instruction_data: DepositInstructionData,
+    pub instruction_data: DepositInstructionData<'a>,
 }
 
 impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Deposit<'a> {
@@ -97,19 +224,135 @@ impl<'a> Deposit<'a> {
     pub const DISCRIMINATOR: &'a u8 = &0;
 
     pub fn process(&mut self) -> ProgramResult {
+        // The vault, record and config addresses all commit to the same
+        // sorted member set, binding the vault to its full multisig
+        // configuration rather than a single key
+        let commitment = hashv(&[self.instruction_data.members]);
+
         // Check vault address
-        let (vault_key, _) = find_program_address(
-            &[
-                b"vault",
-                &self.instruction_data.pubkey[..1],
-                &self.instruction_data.pubkey[1..33],
-            ],
-            &crate::ID,
-        );
+        let (vault_key, _) = find_program_address(&[b"vault", &commitment], &crate::ID);
         if vault_key.ne(self.accounts.vault.key()) {
             return Err(ProgramError::InvalidAccountOwner);
         }
 
+        // Check record address and create it lazily so the withdraw side
+        // can rely on it existing
+        let (record_key, record_bump) =
+            find_program_address(&[VaultRecord::SEED, &commitment], &crate::ID);
+        if record_key.ne(self.accounts.record.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        {
+            let seeds = [
+                Seed::from(VaultRecord::SEED),
+                Seed::from(commitment.as_ref()),
+                Seed::from(&[record_bump]),
+            ];
+            let signers = [Signer::from(&seeds)];
+
+            init_state_account(
+                self.accounts.payer,
+                self.accounts.record,
+                VaultRecord::LEN,
+                &signers,
+            )?;
+        }
+
+        // Check config address and create it lazily, storing the
+        // authorized signer set and threshold
+        let (config_key, config_bump) =
+            find_program_address(&[VaultConfig::SEED, &commitment], &crate::ID);
+        if config_key.ne(self.accounts.config.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        {
+            let seeds = [
+                Seed::from(VaultConfig::SEED),
+                Seed::from(commitment.as_ref()),
+                Seed::from(&[config_bump]),
+            ];
+            let signers = [Signer::from(&seeds)];
+
+            let already_initialized = init_state_account(
+                self.accounts.payer,
+                self.accounts.config,
+                VaultConfig::LEN,
+                &signers,
+            )?;
+
+            if !already_initialized {
+                VaultConfig::write(
+                    self.accounts.config,
+                    self.instruction_data.threshold,
+                    self.instruction_data.members,
+                )?;
+            }
+        }
+
+        // Check vesting address and create it lazily, storing the
+        // linear release schedule for this vault's deposit
+        let (vesting_key, vesting_bump) =
+            find_program_address(&[VaultVesting::SEED, &commitment], &crate::ID);
+        if vesting_key.ne(self.accounts.vesting.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        {
+            let seeds = [
+                Seed::from(VaultVesting::SEED),
+                Seed::from(commitment.as_ref()),
+                Seed::from(&[vesting_bump]),
+            ];
+            let signers = [Signer::from(&seeds)];
+
+            let already_initialized = init_state_account(
+                self.accounts.payer,
+                self.accounts.vesting,
+                VaultVesting::LEN,
+                &signers,
+            )?;
+
+            if !already_initialized {
+                VaultVesting::write(
+                    self.accounts.vesting,
+                    self.instruction_data.start_ts,
+                    self.instruction_data.cliff_ts,
+                    self.instruction_data.end_ts,
+                    self.instruction_data.amount,
+                )?;
+            }
+        }
+
+        // Check whitelist address and create it lazily, storing the
+        // programs this vault's funds may be relayed into
+        let (whitelist_key, whitelist_bump) =
+            find_program_address(&[VaultWhitelist::SEED, &commitment], &crate::ID);
+        if whitelist_key.ne(self.accounts.whitelist.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        {
+            let seeds = [
+                Seed::from(VaultWhitelist::SEED),
+                Seed::from(commitment.as_ref()),
+                Seed::from(&[whitelist_bump]),
+            ];
+            let signers = [Signer::from(&seeds)];
+
+            let already_initialized = init_state_account(
+                self.accounts.payer,
+                self.accounts.whitelist,
+                VaultWhitelist::LEN,
+                &signers,
+            )?;
+
+            if !already_initialized {
+                VaultWhitelist::write(self.accounts.whitelist, self.instruction_data.targets)?;
+            }
+        }
+
         Transfer {
             from: self.accounts.payer,
             to: self.accounts.vault,
@@ -119,4 +362,4 @@ impl<'a> Deposit<'a> {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
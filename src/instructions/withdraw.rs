@@ -2,21 +2,26 @@ use core::mem::size_of;
 
 use pinocchio::{
     ProgramResult,
-    account_info::{AccountInfo, Ref},
+    account_info::AccountInfo,
     instruction::{Seed, Signer},
     program_error::ProgramError,
-    sysvars::{
-        Sysvar,
-        clock::Clock,
-        instructions::{Instructions, IntrospectedInstruction},
-    },
+    pubkey::find_program_address,
+    sysvars::{Sysvar, clock::Clock},
 };
-use pinocchio_secp256r1_instruction::{Secp256r1Instruction, Secp256r1Pubkey};
 use pinocchio_system::instructions::Transfer;
 
+use crate::{
+    introspection::find_secp256r1_instruction,
+    signing::{VerifiedWithdrawal, advance_record, check_nonce_and_cap_amount, verify_withdrawal},
+    state::{VaultConfig, VaultRecord, VaultVesting, hashv},
+};
+
 pub struct WithdrawAccounts<'a> {
     pub payer: &'a AccountInfo,
     pub vault: &'a AccountInfo,
+    pub record: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub vesting: &'a AccountInfo,
     pub instructions: &'a AccountInfo,
     pub system_program: &'a AccountInfo,
 }
@@ -25,7 +30,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawAccounts<'a> {
     type Error = ProgramError;
 
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-        let [payer, vault, instructions, system_program] = accounts else {
+        let [payer, vault, record, config, vesting, instructions, system_program] = accounts
+        else {
             return Err(ProgramError::NotEnoughAccountKeys);
         };
 
@@ -37,9 +43,24 @@ impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawAccounts<'a> {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        if !record.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if !config.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if !vesting.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
         Ok(Self {
             payer,
             vault,
+            record,
+            config,
+            vesting,
             instructions,
             system_program,
         })
@@ -87,43 +108,79 @@ impl<'a> Withdraw<'a> {
     pub const DISCRIMINATOR: &'a u8 = &1;
 
     pub fn process(&mut self) -> ProgramResult {
-        // Deserialize our instructions
-        let instructions: Instructions<Ref<[u8]>> =
-            Instructions::try_from(self.accounts.instructions)?;
-        // Get instruction directly after this one
-        let ix: IntrospectedInstruction = instructions.get_instruction_relative(1)?;
-        // Get Secp256r1 instruction
-        let secp256r1_ix = Secp256r1Instruction::try_from(&ix)?;
-        // Enforce that we only have one signature
-        if secp256r1_ix.num_signatures() != 1 {
-            return Err(ProgramError::InvalidInstructionData);
-        }
-        // Enforce that the signer of the first signature is our PDA owner
-        let signer: Secp256r1Pubkey = *secp256r1_ix.get_signer(0)?;
+        // Find the secp256r1 precompile instruction by program id rather
+        // than trusting a fixed offset
+        let secp256r1_ix = find_secp256r1_instruction(self.accounts.instructions)?;
+
+        // Load the authorized signer set and threshold for this vault
+        let config_data = self.accounts.config.try_borrow_data()?;
+        let (threshold, members) = VaultConfig::read(&config_data)?;
+
+        // The vault/record/config triple all commit to the same sorted
+        // member set
+        let commitment = hashv(&[members]);
+
+        // Require enough distinct authorized members to have signed the
+        // same (payer, expiry, amount, nonce) withdrawal
+        let VerifiedWithdrawal {
+            payer,
+            expiry,
+            amount,
+            nonce,
+            ..
+        } = verify_withdrawal(&secp256r1_ix, members, threshold, 0)?;
 
         // Check that our fee payer is correct
-        let (payer, expiry) = secp256r1_ix.get_message_data(0)?.split_at(32);
         if self.accounts.payer.key().ne(payer) {
             return Err(ProgramError::InvalidAccountOwner);
         }
 
         // Get current timestamp
         let now = Clock::get()?.unix_timestamp;
-        // Get signature expiry timestamp
-        let expiry = i64::from_le_bytes(
-            expiry
-                .try_into()
-                .map_err(|_| ProgramError::InvalidInstructionData)?,
-        );
         if now > expiry {
             return Err(ProgramError::InvalidInstructionData);
         }
 
+        if amount == 0 || amount > self.accounts.vault.lamports() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Check vault address against the committed member set
+        let (vault_key, _) = find_program_address(&[b"vault", &commitment], &crate::ID);
+        if vault_key.ne(self.accounts.vault.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // Check record address and enforce the signed nonce matches the
+        // stored one so a signature set can't be replayed
+        let (record_key, _) = find_program_address(&[VaultRecord::SEED, &commitment], &crate::ID);
+        if record_key.ne(self.accounts.record.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // Check config address matches the commitment derived from its
+        // own stored members, so a forged config can't be substituted
+        let (config_key, _) = find_program_address(&[VaultConfig::SEED, &commitment], &crate::ID);
+        if config_key.ne(self.accounts.config.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // Check vesting address against the same committed member set
+        let (vesting_key, _) =
+            find_program_address(&[VaultVesting::SEED, &commitment], &crate::ID);
+        if vesting_key.ne(self.accounts.vesting.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // Check the signed nonce against the stored one and cap the
+        // requested amount to what has actually vested so far
+        let (stored_nonce, already_withdrawn, amount) =
+            check_nonce_and_cap_amount(self.accounts.record, self.accounts.vesting, now, nonce, amount)?;
+
         // Create signer seeds for our CPI
         let seeds = [
             Seed::from(b"vault"),
-            Seed::from(signer[..1].as_ref()),
-            Seed::from(signer[1..].as_ref()),
+            Seed::from(commitment.as_ref()),
             Seed::from(&self.instruction_data.bump),
         ];
         let signers = [Signer::from(&seeds)];
@@ -131,8 +188,10 @@ impl<'a> Withdraw<'a> {
         Transfer {
             from: self.accounts.vault,
             to: self.accounts.payer,
-            lamports: self.accounts.vault.lamports(),
+            lamports: amount,
         }
-        .invoke_signed(&signers)
+        .invoke_signed(&signers)?;
+
+        advance_record(self.accounts.record, stored_nonce, already_withdrawn, amount)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,294 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction, Seed, Signer},
+    program_error::ProgramError,
+    pubkey::find_program_address,
+    sysvars::{Sysvar, clock::Clock},
+};
+
+use crate::{
+    introspection::find_secp256r1_instruction,
+    signing::{VerifiedWithdrawal, advance_record, check_nonce_and_cap_amount, verify_withdrawal},
+    state::{VaultConfig, VaultRecord, VaultVesting, VaultWhitelist, hashv},
+};
+
+/// Upper bound on how many accounts a relayed CPI can forward, kept small
+/// like the vault's other fixed-capacity state.
+const MAX_RELAY_ACCOUNTS: usize = 8;
+
+pub struct WithdrawRelayAccounts<'a> {
+    pub payer: &'a AccountInfo,
+    pub vault: &'a AccountInfo,
+    pub record: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub vesting: &'a AccountInfo,
+    pub whitelist: &'a AccountInfo,
+    pub target_program: &'a AccountInfo,
+    pub instructions: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+    /// Accounts forwarded into the CPI to `target_program`. The first
+    /// entry must be the vault itself, which is the only account ever
+    /// marked as a signer in the relayed CPI; the vault must not appear
+    /// anywhere else in this list.
+    pub remaining: &'a [AccountInfo],
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawRelayAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        if accounts.len() < 9 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+        let (fixed, remaining) = accounts.split_at(9);
+        let [payer, vault, record, config, vesting, whitelist, target_program, instructions, system_program] =
+            fixed
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if remaining.len() > MAX_RELAY_ACCOUNTS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if !vault.is_owned_by(&pinocchio_system::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if vault.lamports().eq(&0) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if !record.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if !config.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if !vesting.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if !whitelist.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        Ok(Self {
+            payer,
+            vault,
+            record,
+            config,
+            vesting,
+            whitelist,
+            target_program,
+            instructions,
+            system_program,
+            remaining,
+        })
+    }
+}
+
+pub struct WithdrawRelayInstructionData<'a> {
+    pub bump: [u8; 1],
+    /// Opaque instruction data forwarded verbatim to `target_program`.
+    pub cpi_data: &'a [u8],
+}
+
+impl<'a> TryFrom<&'a [u8]> for WithdrawRelayInstructionData<'a> {
+    type Error = ProgramError;
+
+    fn try_from(data: &'a [u8]) -> Result<Self, Self::Error> {
+        if data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let bump = u8::from_le_bytes(data[0..1].try_into().unwrap());
+
+        Ok(Self {
+            bump: [bump],
+            cpi_data: &data[1..],
+        })
+    }
+}
+
+pub struct WithdrawRelay<'a> {
+    pub accounts: WithdrawRelayAccounts<'a>,
+    pub instruction_data: WithdrawRelayInstructionData<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for WithdrawRelay<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = WithdrawRelayAccounts::try_from(accounts)?;
+        let instruction_data = WithdrawRelayInstructionData::try_from(data)?;
+
+        Ok(Self {
+            accounts,
+            instruction_data,
+        })
+    }
+}
+
+impl<'a> WithdrawRelay<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &2;
+
+    pub fn process(&mut self) -> ProgramResult {
+        // Find the secp256r1 precompile instruction by program id rather
+        // than trusting a fixed offset
+        let secp256r1_ix = find_secp256r1_instruction(self.accounts.instructions)?;
+
+        // Load the authorized signer set and threshold for this vault
+        let config_data = self.accounts.config.try_borrow_data()?;
+        let (threshold, members) = VaultConfig::read(&config_data)?;
+
+        let commitment = hashv(&[members]);
+
+        // Require enough distinct authorized members to have signed the
+        // same (payer, expiry, amount, nonce, target) relayed withdrawal
+        let VerifiedWithdrawal {
+            payer,
+            expiry,
+            amount,
+            nonce,
+            extra: target,
+        } = verify_withdrawal(&secp256r1_ix, members, threshold, 32)?;
+
+        if self.accounts.payer.key().ne(payer) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // The signed target must match the program we're about to CPI into
+        if self.accounts.target_program.key().ne(target) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        if now > expiry {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if amount == 0 || amount > self.accounts.vault.lamports() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Check vault/record/config/vesting/whitelist addresses against
+        // the committed member set
+        let (vault_key, _) = find_program_address(&[b"vault", &commitment], &crate::ID);
+        if vault_key.ne(self.accounts.vault.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let (record_key, _) = find_program_address(&[VaultRecord::SEED, &commitment], &crate::ID);
+        if record_key.ne(self.accounts.record.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let (config_key, _) = find_program_address(&[VaultConfig::SEED, &commitment], &crate::ID);
+        if config_key.ne(self.accounts.config.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let (vesting_key, _) =
+            find_program_address(&[VaultVesting::SEED, &commitment], &crate::ID);
+        if vesting_key.ne(self.accounts.vesting.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        let (whitelist_key, _) =
+            find_program_address(&[VaultWhitelist::SEED, &commitment], &crate::ID);
+        if whitelist_key.ne(self.accounts.whitelist.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        // The target program must be on the vault's relay whitelist
+        let whitelist_data = self.accounts.whitelist.try_borrow_data()?;
+        let targets = VaultWhitelist::read(&whitelist_data)?;
+        if !VaultWhitelist::contains(targets, self.accounts.target_program.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        drop(whitelist_data);
+
+        // Check the signed nonce against the stored one and cap the
+        // requested amount to what has actually vested so far
+        let (stored_nonce, already_withdrawn, amount) =
+            check_nonce_and_cap_amount(self.accounts.record, self.accounts.vesting, now, nonce, amount)?;
+
+        // The vault may only ever sign as the first forwarded account.
+        // This is the sole account the signed message authorizes the CPI
+        // to move lamports out of; it must appear there exactly once, and
+        // nowhere else, so a caller can't smuggle it in as an extra
+        // "remaining" account to pick up an unintended signer role.
+        let Some((vault_slot, rest)) = self.accounts.remaining.split_first() else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+        if vault_slot.key().ne(self.accounts.vault.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        if rest
+            .iter()
+            .any(|account| account.key().eq(self.accounts.vault.key()))
+        {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // Create signer seeds for our CPI
+        let seeds = [
+            Seed::from(b"vault"),
+            Seed::from(commitment.as_ref()),
+            Seed::from(&self.instruction_data.bump),
+        ];
+        let signers = [Signer::from(&seeds)];
+
+        // Forward the caller-supplied accounts and data into the
+        // whitelisted target program; only the vault slot checked above
+        // may carry the vault's signer role, every other account is
+        // forwarded with whatever writability the caller marked it with
+        let metas: [AccountMeta; MAX_RELAY_ACCOUNTS] = core::array::from_fn(|i| {
+            self.accounts
+                .remaining
+                .get(i)
+                .map(|account| match (i == 0, account.is_writable()) {
+                    (true, true) => AccountMeta::writable_signer(account.key()),
+                    (true, false) => AccountMeta::readonly_signer(account.key()),
+                    (false, true) => AccountMeta::writable(account.key()),
+                    (false, false) => AccountMeta::readonly(account.key()),
+                })
+                .unwrap_or(AccountMeta::readonly(&crate::ID))
+        });
+        let infos: [&AccountInfo; MAX_RELAY_ACCOUNTS] =
+            core::array::from_fn(|i| self.accounts.remaining.get(i).unwrap_or(self.accounts.vault));
+
+        let cpi_instruction = Instruction {
+            program_id: self.accounts.target_program.key(),
+            accounts: &metas[..self.accounts.remaining.len()],
+            data: self.instruction_data.cpi_data,
+        };
+
+        // Snapshot the vault's balance so we can bound what the relayed
+        // CPI is actually allowed to move, independent of however
+        // `cpi_data` shapes the target program's own instruction
+        let vault_before = self.accounts.vault.lamports();
+
+        pinocchio::cpi::invoke_signed(
+            &cpi_instruction,
+            &infos[..self.accounts.remaining.len()],
+            &signers,
+        )?;
+
+        // The target program is opaque: it could move the whole vault
+        // balance regardless of the signed `amount`. Enforce the signed
+        // and vesting-derived caps against what actually left the vault,
+        // and advance `withdrawn` by that observed delta rather than the
+        // signed amount, so accounting can never fall behind reality.
+        let moved = vault_before.saturating_sub(self.accounts.vault.lamports());
+        if moved > amount {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        advance_record(self.accounts.record, stored_nonce, already_withdrawn, moved)
+    }
+}
@@ -0,0 +1,48 @@
+use pinocchio::{
+    account_info::{AccountInfo, Ref},
+    program_error::ProgramError,
+    sysvars::instructions::Instructions,
+};
+use pinocchio_secp256r1_instruction::Secp256r1Instruction;
+
+/// Upper bound on the number of instructions a single transaction can
+/// carry, used to bound the introspection scan below.
+const MAX_SCAN_RANGE: i64 = 64;
+
+/// Locates the secp256r1 precompile verification instruction for the
+/// currently executing instruction.
+///
+/// Rather than trusting a fixed offset (which breaks the moment the
+/// transaction is reordered or padded with extra instructions), this
+/// scans every instruction relative to ours by `program_id` — accepting
+/// only a precompile instruction placed immediately before or after us —
+/// and rejects the transaction outright if the precompile appears zero
+/// times or more than once anywhere in it.
+pub fn find_secp256r1_instruction<'a>(
+    instructions_sysvar: &'a AccountInfo,
+) -> Result<Secp256r1Instruction<'a>, ProgramError> {
+    let instructions: Instructions<Ref<'a, [u8]>> = Instructions::try_from(instructions_sysvar)?;
+
+    let mut found: Option<Secp256r1Instruction<'a>> = None;
+    let mut matches: u32 = 0;
+
+    for offset in -MAX_SCAN_RANGE..=MAX_SCAN_RANGE {
+        let Ok(ix) = instructions.get_instruction_relative(offset) else {
+            continue;
+        };
+        let Ok(secp256r1_ix) = Secp256r1Instruction::try_from(&ix) else {
+            continue;
+        };
+
+        matches += 1;
+        if offset == -1 || offset == 1 {
+            found = Some(secp256r1_ix);
+        }
+    }
+
+    if matches != 1 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    found.ok_or(ProgramError::InvalidInstructionData)
+}
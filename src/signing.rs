@@ -0,0 +1,131 @@
+use core::mem::size_of;
+
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError};
+use pinocchio_secp256r1_instruction::{
+    SECP256R1_COMPRESSED_PUBKEY_LENGTH, Secp256r1Instruction, Secp256r1Pubkey,
+};
+
+use crate::state::{MAX_SIGNERS, VaultRecord, VaultVesting, count_distinct_signers};
+
+/// A withdrawal authorized by one or more secp256r1 signatures.
+///
+/// `extra` is whatever trailing bytes follow `nonce` in the signed
+/// message, letting callers with a richer message (e.g. `WithdrawRelay`'s
+/// signed target program) parse their own fields without duplicating the
+/// signature-gathering/threshold logic below.
+pub struct VerifiedWithdrawal<'a> {
+    pub payer: &'a [u8],
+    pub expiry: i64,
+    pub amount: u64,
+    pub nonce: u64,
+    pub extra: &'a [u8],
+}
+
+/// Walks every secp256r1 signature on `secp256r1_ix`, requiring each to
+/// authorize the same `payer(32) || expiry(8) || amount(8) || nonce(8) ||
+/// extra` message and to come from a distinct member of `members` (the
+/// raw, sorted concatenation of compressed secp256r1 pubkeys stored in a
+/// vault's `VaultConfig`), then checks at least `threshold` distinct
+/// members signed.
+///
+/// Shared by `Withdraw` and `WithdrawRelay` so the two paths can't drift
+/// out of sync on how a withdrawal is authorized.
+pub fn verify_withdrawal<'a>(
+    secp256r1_ix: &Secp256r1Instruction<'a>,
+    members: &[u8],
+    threshold: u8,
+    extra_len: usize,
+) -> Result<VerifiedWithdrawal<'a>, ProgramError> {
+    let num_signatures = secp256r1_ix.num_signatures();
+    if num_signatures == 0 || num_signatures as usize > MAX_SIGNERS {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let mut signer_bytes = [[0u8; SECP256R1_COMPRESSED_PUBKEY_LENGTH]; MAX_SIGNERS];
+    let mut withdrawal: Option<(&[u8], i64, u64, u64, &[u8])> = None;
+
+    for i in 0..num_signatures {
+        let signer: Secp256r1Pubkey = *secp256r1_ix.get_signer(i)?;
+
+        let message = secp256r1_ix.get_message_data(i)?;
+        if message.len() != 32 + size_of::<i64>() + size_of::<u64>() + size_of::<u64>() + extra_len
+        {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        let (payer, rest) = message.split_at(32);
+        let (expiry, rest) = rest.split_at(size_of::<i64>());
+        let (amount, rest) = rest.split_at(size_of::<u64>());
+        let (nonce, extra) = rest.split_at(size_of::<u64>());
+        let expiry = i64::from_le_bytes(expiry.try_into().unwrap());
+        let amount = u64::from_le_bytes(amount.try_into().unwrap());
+        let nonce = u64::from_le_bytes(nonce.try_into().unwrap());
+
+        match withdrawal {
+            None => withdrawal = Some((payer, expiry, amount, nonce, extra)),
+            Some((p, e, a, n, ex)) => {
+                if p.ne(payer) || e.ne(&expiry) || a.ne(&amount) || n.ne(&nonce) || ex.ne(extra) {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+            }
+        }
+
+        signer_bytes[i as usize].copy_from_slice(signer.as_ref());
+    }
+
+    let signer_refs: [&[u8]; MAX_SIGNERS] = core::array::from_fn(|i| signer_bytes[i].as_ref());
+    let valid_signers = count_distinct_signers(members, &signer_refs[..num_signatures as usize]);
+    if valid_signers != num_signatures as usize || valid_signers < threshold as usize {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (payer, expiry, amount, nonce, extra) =
+        withdrawal.ok_or(ProgramError::InvalidInstructionData)?;
+
+    Ok(VerifiedWithdrawal {
+        payer,
+        expiry,
+        amount,
+        nonce,
+        extra,
+    })
+}
+
+/// Checks the signed `nonce` against the record's stored nonce, then caps
+/// `requested_amount` to what has actually vested and not yet been
+/// withdrawn. Returns `(stored_nonce, already_withdrawn, capped_amount)`.
+pub fn check_nonce_and_cap_amount(
+    record: &AccountInfo,
+    vesting: &AccountInfo,
+    now: i64,
+    nonce: u64,
+    requested_amount: u64,
+) -> Result<(u64, u64, u64), ProgramError> {
+    let stored_nonce = VaultRecord::read_nonce(record)?;
+    if nonce.ne(&stored_nonce) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    let (start_ts, cliff_ts, end_ts, total_deposited) = VaultVesting::read(vesting)?;
+    let vested = VaultVesting::vested_at(now, start_ts, cliff_ts, end_ts, total_deposited)?;
+    let already_withdrawn = VaultRecord::read_withdrawn(record)?;
+    let releasable = vested.saturating_sub(already_withdrawn);
+    let amount = requested_amount.min(releasable);
+    if amount == 0 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+
+    Ok((stored_nonce, already_withdrawn, amount))
+}
+
+/// Bumps the stored nonce and withdrawn total after a successful
+/// withdrawal, so a signature set can't be replayed and future
+/// withdrawals see the right balance.
+pub fn advance_record(
+    record: &AccountInfo,
+    stored_nonce: u64,
+    already_withdrawn: u64,
+    moved: u64,
+) -> Result<(), ProgramError> {
+    VaultRecord::write_nonce(record, stored_nonce + 1)?;
+    VaultRecord::write_withdrawn(record, already_withdrawn + moved)
+}
@@ -0,0 +1,357 @@
+use core::mem::size_of;
+
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+use pinocchio_secp256r1_instruction::SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+
+/// Maximum number of secp256r1 members a vault's multisig can authorize.
+pub const MAX_SIGNERS: usize = 10;
+
+/// Replay-protection and vesting-progress record for a vault.
+///
+/// Created lazily alongside the vault and updated on every successful
+/// withdrawal, modeled on the SPL record program's fixed-layout
+/// create/update account: a monotonic `u64` nonce plus the cumulative
+/// lamports released so far against the vault's vesting schedule.
+pub struct VaultRecord;
+
+impl VaultRecord {
+    pub const SEED: &'static [u8] = b"vault_record";
+    pub const LEN: usize = size_of::<u64>() + size_of::<u64>();
+
+    pub fn read_nonce(account: &AccountInfo) -> Result<u64, ProgramError> {
+        let data = account.try_borrow_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(u64::from_le_bytes(data[0..8].try_into().unwrap()))
+    }
+
+    pub fn write_nonce(account: &AccountInfo, nonce: u64) -> Result<(), ProgramError> {
+        let mut data = account.try_borrow_mut_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        data[0..8].copy_from_slice(&nonce.to_le_bytes());
+
+        Ok(())
+    }
+
+    pub fn read_withdrawn(account: &AccountInfo) -> Result<u64, ProgramError> {
+        let data = account.try_borrow_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(u64::from_le_bytes(data[8..16].try_into().unwrap()))
+    }
+
+    pub fn write_withdrawn(account: &AccountInfo, withdrawn: u64) -> Result<(), ProgramError> {
+        let mut data = account.try_borrow_mut_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        data[8..16].copy_from_slice(&withdrawn.to_le_bytes());
+
+        Ok(())
+    }
+}
+
+/// Multisig configuration for a vault.
+///
+/// Stores the authorized secp256r1 signer set (up to [`MAX_SIGNERS`]
+/// compressed pubkeys, in canonical sorted order) and the threshold
+/// number of signatures required to withdraw. Created lazily alongside
+/// the vault, from the same `Deposit` call that establishes the record.
+pub struct VaultConfig;
+
+impl VaultConfig {
+    pub const SEED: &'static [u8] = b"vault_config";
+    pub const LEN: usize = 2 + MAX_SIGNERS * SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+
+    /// `members` is the raw, already-sorted concatenation of compressed
+    /// secp256r1 pubkeys (`num_members * 33` bytes).
+    pub fn write(account: &AccountInfo, threshold: u8, members: &[u8]) -> Result<(), ProgramError> {
+        let num_members = members.len() / SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+        if num_members == 0 || num_members > MAX_SIGNERS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut data = account.try_borrow_mut_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        data[0] = threshold;
+        data[1] = num_members as u8;
+        data[2..2 + members.len()].copy_from_slice(members);
+
+        Ok(())
+    }
+
+    /// Returns `(threshold, members)` where `members` is the raw sorted
+    /// concatenation of compressed secp256r1 pubkeys stored on-chain.
+    pub fn read(data: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let threshold = data[0];
+        let num_members = data[1] as usize;
+        if threshold == 0 || num_members == 0 || num_members > MAX_SIGNERS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok((
+            threshold,
+            &data[2..2 + num_members * SECP256R1_COMPRESSED_PUBKEY_LENGTH],
+        ))
+    }
+}
+
+/// Linear vesting schedule for a vault's deposited lamports.
+///
+/// Created alongside the vault from its single `Deposit` call and never
+/// updated afterwards: `total_deposited` vests linearly between
+/// `start_ts` and `end_ts`, with nothing releasable before the cliff;
+/// at `cliff_ts` the share accrued since `start_ts` becomes releasable.
+pub struct VaultVesting;
+
+impl VaultVesting {
+    pub const SEED: &'static [u8] = b"vault_vesting";
+    pub const LEN: usize = size_of::<i64>() * 3 + size_of::<u64>();
+
+    pub fn write(
+        account: &AccountInfo,
+        start_ts: i64,
+        cliff_ts: i64,
+        end_ts: i64,
+        total_deposited: u64,
+    ) -> Result<(), ProgramError> {
+        let mut data = account.try_borrow_mut_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        data[0..8].copy_from_slice(&start_ts.to_le_bytes());
+        data[8..16].copy_from_slice(&cliff_ts.to_le_bytes());
+        data[16..24].copy_from_slice(&end_ts.to_le_bytes());
+        data[24..32].copy_from_slice(&total_deposited.to_le_bytes());
+
+        Ok(())
+    }
+
+    /// Returns `(start_ts, cliff_ts, end_ts, total_deposited)`.
+    pub fn read(account: &AccountInfo) -> Result<(i64, i64, i64, u64), ProgramError> {
+        let data = account.try_borrow_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let start_ts = i64::from_le_bytes(data[0..8].try_into().unwrap());
+        let cliff_ts = i64::from_le_bytes(data[8..16].try_into().unwrap());
+        let end_ts = i64::from_le_bytes(data[16..24].try_into().unwrap());
+        let total_deposited = u64::from_le_bytes(data[24..32].try_into().unwrap());
+
+        Ok((start_ts, cliff_ts, end_ts, total_deposited))
+    }
+
+    /// Amount vested at `now`, using checked 128-bit intermediate math to
+    /// avoid overflow on `total_deposited * elapsed`.
+    pub fn vested_at(
+        now: i64,
+        start_ts: i64,
+        cliff_ts: i64,
+        end_ts: i64,
+        total_deposited: u64,
+    ) -> Result<u64, ProgramError> {
+        if now < cliff_ts {
+            return Ok(0);
+        }
+        if now >= end_ts {
+            return Ok(total_deposited);
+        }
+
+        let elapsed = (now - start_ts) as u128;
+        let duration = (end_ts - start_ts) as u128;
+
+        let vested = (total_deposited as u128)
+            .checked_mul(elapsed)
+            .and_then(|v| v.checked_div(duration))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        u64::try_from(vested).map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+}
+
+/// Maximum number of programs a vault can relay withdrawals into.
+pub const MAX_TARGETS: usize = 5;
+
+/// Whitelist of programs a vault's funds may be relayed into via CPI
+/// instead of being transferred to the withdrawing payer.
+///
+/// Created alongside the vault from its single `Deposit` call, like
+/// [`VaultConfig`] and [`VaultVesting`].
+pub struct VaultWhitelist;
+
+impl VaultWhitelist {
+    pub const SEED: &'static [u8] = b"vault_whitelist";
+    pub const LEN: usize = 1 + MAX_TARGETS * size_of::<Pubkey>();
+
+    /// `targets` is the raw concatenation of whitelisted program ids
+    /// (`num_targets * 32` bytes).
+    pub fn write(account: &AccountInfo, targets: &[u8]) -> Result<(), ProgramError> {
+        let num_targets = targets.len() / size_of::<Pubkey>();
+        if num_targets == 0 || num_targets > MAX_TARGETS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut data = account.try_borrow_mut_data()?;
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        data[0] = num_targets as u8;
+        data[1..1 + targets.len()].copy_from_slice(targets);
+
+        Ok(())
+    }
+
+    /// Returns the raw concatenation of whitelisted program ids stored
+    /// on-chain.
+    pub fn read(data: &[u8]) -> Result<&[u8], ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let num_targets = data[0] as usize;
+        if num_targets == 0 || num_targets > MAX_TARGETS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(&data[1..1 + num_targets * size_of::<Pubkey>()])
+    }
+
+    pub fn contains(targets: &[u8], target: &Pubkey) -> bool {
+        targets
+            .chunks(size_of::<Pubkey>())
+            .any(|candidate| candidate.eq(target.as_ref()))
+    }
+}
+
+/// Counts how many distinct members of `members` (a raw, sorted
+/// concatenation of compressed secp256r1 pubkeys) appear in `signers`.
+///
+/// A member only counts once even if `signers` contains it more than
+/// once, so a threshold check against the result can't be satisfied by
+/// repeating a single valid signature.
+pub fn count_distinct_signers(members: &[u8], signers: &[&[u8]]) -> usize {
+    let num_members = members.len() / SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+    let mut used = [false; MAX_SIGNERS];
+    let mut valid_signers = 0;
+
+    for signer in signers {
+        for m in 0..num_members {
+            let offset = m * SECP256R1_COMPRESSED_PUBKEY_LENGTH;
+            let member = &members[offset..offset + SECP256R1_COMPRESSED_PUBKEY_LENGTH];
+            if member.eq(*signer) && !used[m] {
+                used[m] = true;
+                valid_signers += 1;
+                break;
+            }
+        }
+    }
+
+    valid_signers
+}
+
+/// SHA-256 over the concatenation of `vals`, used to commit a vault's
+/// sorted multisig member set into its PDA seeds.
+pub fn hashv(vals: &[&[u8]]) -> [u8; 32] {
+    let mut hash_result = [0u8; 32];
+    unsafe {
+        pinocchio::syscalls::sol_sha256(
+            vals as *const _ as *const u8,
+            vals.len() as u64,
+            &mut hash_result as *mut _ as *mut u8,
+        );
+    }
+    hash_result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(tag: u8) -> [u8; SECP256R1_COMPRESSED_PUBKEY_LENGTH] {
+        [tag; SECP256R1_COMPRESSED_PUBKEY_LENGTH]
+    }
+
+    #[test]
+    fn count_distinct_signers_counts_each_member_once() {
+        let m1 = member(1);
+        let m2 = member(2);
+        let m3 = member(3);
+        let members = [m1, m2, m3].concat();
+
+        // A single valid signer counts once.
+        assert_eq!(count_distinct_signers(&members, &[&m1]), 1);
+
+        // Two distinct valid signers count twice.
+        assert_eq!(count_distinct_signers(&members, &[&m1, &m2]), 2);
+
+        // Repeating the same signer must not count it twice.
+        assert_eq!(count_distinct_signers(&members, &[&m1, &m1]), 1);
+    }
+
+    #[test]
+    fn count_distinct_signers_ignores_unknown_keys() {
+        let m1 = member(1);
+        let m2 = member(2);
+        let unknown = member(9);
+        let members = [m1, m2].concat();
+
+        assert_eq!(count_distinct_signers(&members, &[&unknown]), 0);
+        assert_eq!(count_distinct_signers(&members, &[&m1, &unknown]), 1);
+    }
+
+    #[test]
+    fn vested_at_before_cliff_is_zero() {
+        let vested = VaultVesting::vested_at(50, 0, 100, 200, 1_000).unwrap();
+        assert_eq!(vested, 0);
+    }
+
+    #[test]
+    fn vested_at_cliff_boundary_is_linear_not_zero() {
+        // At exactly the cliff, release has begun: elapsed/duration = 100/200.
+        let vested = VaultVesting::vested_at(100, 0, 100, 200, 1_000).unwrap();
+        assert_eq!(vested, 500);
+    }
+
+    #[test]
+    fn vested_at_end_boundary_is_fully_vested() {
+        let vested = VaultVesting::vested_at(200, 0, 100, 200, 1_000).unwrap();
+        assert_eq!(vested, 1_000);
+
+        // Anything at or past the end is capped at the total, never more.
+        let vested_after_end = VaultVesting::vested_at(1_000_000, 0, 100, 200, 1_000).unwrap();
+        assert_eq!(vested_after_end, 1_000);
+    }
+
+    #[test]
+    fn vested_at_mid_schedule_is_proportional() {
+        let vested = VaultVesting::vested_at(150, 0, 100, 200, 1_000).unwrap();
+        assert_eq!(vested, 750);
+    }
+
+    #[test]
+    fn vested_at_does_not_overflow_on_large_deposits() {
+        // total_deposited near u64::MAX must not overflow the checked
+        // 128-bit intermediate multiplication.
+        let vested = VaultVesting::vested_at(150, 0, 100, 200, u64::MAX).unwrap();
+        let expected = ((u64::MAX as u128) * 150 / 200) as u64;
+        assert_eq!(vested, expected);
+    }
+}